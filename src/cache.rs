@@ -0,0 +1,212 @@
+//! Content-hashed cache of extracted clips, so repeated requests for the same
+//! source, offset and duration don't re-run `ffmpeg`.
+//!
+//! The cache keeps a small read/write SQLite index (separate from the
+//! read-only Voice Memos database opened by [`crate::VoiceMemoLibrary`])
+//! mapping a digest of the clip's inputs to the clip file on disk.
+
+use crate::{Clip, TrimMode};
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A persistent, content-addressed store of extracted clips.
+pub struct ClipCache {
+    dir: PathBuf,
+    index: Connection,
+}
+
+impl ClipCache {
+    /// The default cache directory, under the user's Library/Caches.
+    pub fn default_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let home = std::env::var("HOME").map_err(|_| "HOME environment variable not set")?;
+        Ok(PathBuf::from(home)
+            .join("Library")
+            .join("Caches")
+            .join("voice-memo-clip-player"))
+    }
+
+    /// Opens (creating if necessary) the cache directory and its index at `dir`.
+    pub fn open(dir: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(&dir)?;
+
+        let index = Connection::open(dir.join("index.sqlite3"))?;
+        index.execute(
+            "CREATE TABLE IF NOT EXISTS clips (
+                key         TEXT PRIMARY KEY,
+                clip_path   TEXT NOT NULL,
+                created_at  INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(Self { dir, index })
+    }
+
+    /// Returns the cached clip for (`relative_path`, `start_sec`,
+    /// `duration_sec`), extracting and recording it first on a cache miss.
+    /// `source_path` is the absolute path ffmpeg actually reads; `relative_path`
+    /// (the memo's path relative to the Voice Memos recordings directory) is
+    /// what gets hashed into the cache key, so the cache stays portable across
+    /// machines with the recordings mounted at different absolute paths.
+    pub fn get_or_extract(
+        &self,
+        source_path: &Path,
+        relative_path: &str,
+        start_sec: f64,
+        duration_sec: f64,
+        original_date: DateTime<Utc>,
+        trim_mode: TrimMode,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let key = Self::key_for(source_path, relative_path, start_sec, duration_sec, trim_mode)?;
+
+        if let Some(path) = self.get(&key) {
+            return Ok(path);
+        }
+
+        let dest_path = self.clip_path(&key);
+        Clip::extract(
+            source_path,
+            &dest_path,
+            start_sec,
+            duration_sec,
+            original_date,
+            trim_mode,
+        )?;
+        self.insert(&key, &dest_path)?;
+
+        Ok(dest_path)
+    }
+
+    /// Deletes cache entries (and their clip files) created more than `days` ago.
+    /// Returns the number of entries purged.
+    pub fn purge_older_than(&self, days: u64) -> Result<usize, Box<dyn std::error::Error>> {
+        let cutoff = now_unix()? - (days as i64 * 86_400);
+
+        let mut stmt = self
+            .index
+            .prepare("SELECT clip_path FROM clips WHERE created_at < ?1")?;
+        let stale: Vec<String> = stmt
+            .query_map([cutoff], |row| row.get(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        for clip_path in &stale {
+            let _ = std::fs::remove_file(clip_path);
+        }
+        self.index
+            .execute("DELETE FROM clips WHERE created_at < ?1", [cutoff])?;
+
+        Ok(stale.len())
+    }
+
+    fn get(&self, key: &str) -> Option<PathBuf> {
+        let clip_path: String = self
+            .index
+            .query_row("SELECT clip_path FROM clips WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .ok()?;
+        let clip_path = PathBuf::from(clip_path);
+        clip_path.exists().then_some(clip_path)
+    }
+
+    fn insert(&self, key: &str, clip_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.index.execute(
+            "INSERT OR REPLACE INTO clips (key, clip_path, created_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![key, clip_path.to_string_lossy(), now_unix()?],
+        )?;
+        Ok(())
+    }
+
+    fn clip_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.m4a", key))
+    }
+
+    /// Hashes the recording's relative path, file size, start offset,
+    /// duration and trim mode into a stable cache key. `trim_mode` must be
+    /// included: otherwise a cached `EditList` clip would be handed back for
+    /// a `--reencode` request at the same offset, silently ignoring the flag.
+    /// `relative_path` (not `source_path`) is what's hashed, so the key
+    /// doesn't embed a machine-specific absolute path.
+    fn key_for(
+        source_path: &Path,
+        relative_path: &str,
+        start_sec: f64,
+        duration_sec: f64,
+        trim_mode: TrimMode,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let file_size = std::fs::metadata(source_path)?.len();
+
+        let mut hasher = Sha256::new();
+        hasher.update(relative_path.as_bytes());
+        hasher.update(file_size.to_le_bytes());
+        hasher.update(start_sec.to_bits().to_le_bytes());
+        hasher.update(duration_sec.to_bits().to_le_bytes());
+        hasher.update([trim_mode as u8]);
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+}
+
+fn now_unix() -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_source_file(name: &str, contents: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn key_for_is_deterministic() {
+        let source = temp_source_file("cache_key_for_deterministic.m4a", b"abc");
+        let key_a =
+            ClipCache::key_for(&source, "Recordings/a.m4a", 1.0, 30.0, TrimMode::EditList)
+                .unwrap();
+        let key_b =
+            ClipCache::key_for(&source, "Recordings/a.m4a", 1.0, 30.0, TrimMode::EditList)
+                .unwrap();
+        assert_eq!(key_a, key_b);
+    }
+
+    #[test]
+    fn key_for_differs_by_relative_path_not_source_path() {
+        let source_1 = temp_source_file("cache_key_for_path_a.m4a", b"abc");
+        let source_2 = temp_source_file("cache_key_for_path_b.m4a", b"abc");
+
+        // Same relative path, different absolute source paths: same key.
+        let key_1 =
+            ClipCache::key_for(&source_1, "Recordings/a.m4a", 1.0, 30.0, TrimMode::EditList)
+                .unwrap();
+        let key_2 =
+            ClipCache::key_for(&source_2, "Recordings/a.m4a", 1.0, 30.0, TrimMode::EditList)
+                .unwrap();
+        assert_eq!(key_1, key_2);
+
+        // Different relative path: different key.
+        let key_3 =
+            ClipCache::key_for(&source_1, "Recordings/b.m4a", 1.0, 30.0, TrimMode::EditList)
+                .unwrap();
+        assert_ne!(key_1, key_3);
+    }
+
+    #[test]
+    fn key_for_differs_by_trim_mode() {
+        let source = temp_source_file("cache_key_for_trim_mode.m4a", b"abc");
+        let edit_list =
+            ClipCache::key_for(&source, "Recordings/a.m4a", 1.0, 30.0, TrimMode::EditList)
+                .unwrap();
+        let reencode =
+            ClipCache::key_for(&source, "Recordings/a.m4a", 1.0, 30.0, TrimMode::Reencode)
+                .unwrap();
+        assert_ne!(edit_list, reencode);
+    }
+}
@@ -0,0 +1,41 @@
+//! Error type returned by [`crate::VoiceMemoLibrary`].
+
+use std::fmt;
+
+/// Errors reading the Voice Memos database.
+#[derive(Debug)]
+pub enum VoiceMemoError {
+    /// A SQLite error unrelated to schema shape (e.g. the database is locked).
+    Sql(rusqlite::Error),
+    /// `ZCLOUDRECORDING` is missing columns this crate can't do without,
+    /// reported with the column set that was actually found so the caller
+    /// can tell which macOS/Voice Memos schema variant they're on.
+    UnsupportedSchema(String),
+    /// The `HOME` environment variable isn't set, so the Voice Memos
+    /// recordings directory can't be located.
+    MissingHomeDir,
+}
+
+impl fmt::Display for VoiceMemoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoiceMemoError::Sql(err) => write!(f, "{}", err),
+            VoiceMemoError::UnsupportedSchema(msg) => {
+                write!(f, "unsupported Voice Memos database schema: {}", msg)
+            }
+            VoiceMemoError::MissingHomeDir => {
+                write!(f, "HOME environment variable not set")
+            }
+        }
+    }
+}
+
+impl std::error::Error for VoiceMemoError {}
+
+impl From<rusqlite::Error> for VoiceMemoError {
+    fn from(err: rusqlite::Error) -> Self {
+        VoiceMemoError::Sql(err)
+    }
+}
+
+pub type VoiceMemoResult<T> = std::result::Result<T, VoiceMemoError>;
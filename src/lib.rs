@@ -0,0 +1,428 @@
+//! Library for reading Apple Voice Memos and extracting clips from them.
+//!
+//! This crate is read-only with respect to the Voice Memos database and its
+//! recordings: [`VoiceMemoLibrary`] only ever opens `CloudRecordings.db` with
+//! [`OpenFlags::SQLITE_OPEN_READ_ONLY`], and [`Clip::extract`] writes nothing
+//! back to the source file. Embedding programs can use this API directly
+//! instead of shelling out to the `voice-memo-clip-player` binary.
+
+mod cache;
+mod error;
+
+pub use cache::ClipCache;
+pub use error::VoiceMemoError;
+
+use chrono::{DateTime, Utc};
+use error::VoiceMemoResult;
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A single Voice Memos recording, as read from `ZCLOUDRECORDING`.
+#[derive(Debug, Clone)]
+pub struct VoiceMemo {
+    pub title: String,
+    /// Recording date as a Core Data timestamp (seconds since 2001-01-01 UTC).
+    pub date: f64,
+    pub duration: f64,
+    /// Path to the `.m4a` file, relative to the Voice Memos recordings directory.
+    pub path: String,
+}
+
+impl VoiceMemo {
+    /// The recording date converted to a UTC [`DateTime`].
+    pub fn date_utc(&self) -> DateTime<Utc> {
+        let unix_timestamp = core_data_to_unix_timestamp(self.date);
+        DateTime::<Utc>::from_timestamp(unix_timestamp, 0).unwrap_or_else(Utc::now)
+    }
+}
+
+/// Converts a Core Data reference-date timestamp to a Unix timestamp.
+///
+/// Core Data reference date is January 1, 2001 00:00:00 UTC; Unix epoch is
+/// January 1, 1970 00:00:00 UTC. The two are 978307200 seconds apart.
+pub fn core_data_to_unix_timestamp(core_data_timestamp: f64) -> i64 {
+    const CORE_DATA_EPOCH_OFFSET: f64 = 978307200.0;
+    (core_data_timestamp + CORE_DATA_EPOCH_OFFSET) as i64
+}
+
+/// Read-only handle onto a user's Voice Memos library.
+pub struct VoiceMemoLibrary {
+    conn: Connection,
+    recordings_dir: PathBuf,
+}
+
+impl VoiceMemoLibrary {
+    /// Opens the local Voice Memos database in read-only mode.
+    pub fn open_readonly() -> VoiceMemoResult<Self> {
+        let recordings_dir = Self::recordings_dir()?;
+        let db_path = recordings_dir.join("CloudRecordings.db");
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(Self {
+            conn,
+            recordings_dir,
+        })
+    }
+
+    fn recordings_dir() -> VoiceMemoResult<PathBuf> {
+        let home = std::env::var("HOME").map_err(|_| VoiceMemoError::MissingHomeDir)?;
+        Ok(PathBuf::from(home)
+            .join("Library")
+            .join("Group Containers")
+            .join("group.com.apple.VoiceMemos.shared")
+            .join("Recordings"))
+    }
+
+    /// Every recording in the library, regardless of duration.
+    ///
+    /// Resolves each logical field (title, date, duration, path) to whatever
+    /// column actually exists on `ZCLOUDRECORDING`, since the table layout
+    /// shifts across macOS versions. A missing title or duration column falls
+    /// back to a default with a warning rather than aborting the whole read;
+    /// a missing date or path column is reported as
+    /// [`VoiceMemoError::UnsupportedSchema`].
+    pub fn all_memos(&self) -> VoiceMemoResult<Vec<VoiceMemo>> {
+        let schema = ZCloudRecordingSchema::probe(&self.conn)?;
+
+        let query = format!(
+            "SELECT {}, {}, {}, {} FROM ZCLOUDRECORDING",
+            schema.title_expr, schema.date_column, schema.duration_expr, schema.path_column
+        );
+        let mut stmt = self.conn.prepare(&query)?;
+
+        let memos = stmt
+            .query_map([], |row| {
+                let title: Option<String> = row.get(0)?;
+                Ok(VoiceMemo {
+                    title: title.unwrap_or_else(|| "Untitled".to_string()),
+                    date: row.get(1)?,
+                    duration: row.get(2)?,
+                    path: row.get(3)?,
+                })
+            })?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        Ok(memos)
+    }
+
+    /// Memos matching `predicate`, e.g. a minimum duration or title substring.
+    pub fn filter<F>(&self, predicate: F) -> VoiceMemoResult<Vec<VoiceMemo>>
+    where
+        F: FnMut(&VoiceMemo) -> bool,
+    {
+        let mut predicate = predicate;
+        Ok(self
+            .all_memos()?
+            .into_iter()
+            .filter(|memo| predicate(memo))
+            .collect())
+    }
+
+    /// Resolves a memo's recording path against the Voice Memos recordings directory.
+    pub fn recording_path(&self, memo: &VoiceMemo) -> PathBuf {
+        self.recordings_dir.join(&memo.path)
+    }
+}
+
+/// SQL expressions resolved from the actual columns present on
+/// `ZCLOUDRECORDING`, probed via `PRAGMA table_info` instead of assumed.
+struct ZCloudRecordingSchema {
+    title_expr: String,
+    date_column: String,
+    duration_expr: String,
+    path_column: String,
+}
+
+impl ZCloudRecordingSchema {
+    fn probe(conn: &Connection) -> VoiceMemoResult<Self> {
+        let mut stmt = conn.prepare("PRAGMA table_info(ZCLOUDRECORDING)")?;
+        let columns: Vec<String> = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        if columns.is_empty() {
+            return Err(VoiceMemoError::UnsupportedSchema(
+                "ZCLOUDRECORDING table not found or has no columns".to_string(),
+            ));
+        }
+        let has = |name: &str| columns.iter().any(|c| c == name);
+
+        let title_expr = match (has("ZENCRYPTEDTITLE"), has("ZCUSTOMLABEL")) {
+            (true, true) => "COALESCE(ZENCRYPTEDTITLE, ZCUSTOMLABEL)".to_string(),
+            (true, false) => "ZENCRYPTEDTITLE".to_string(),
+            (false, true) => "ZCUSTOMLABEL".to_string(),
+            (false, false) => {
+                eprintln!(
+                    "warning: ZCLOUDRECORDING has no title column; titles will default to \"Untitled\""
+                );
+                "NULL".to_string()
+            }
+        };
+
+        let date_column = has("ZDATE").then(|| "ZDATE".to_string()).ok_or_else(|| {
+            VoiceMemoError::UnsupportedSchema(format!(
+                "ZCLOUDRECORDING has no ZDATE column; found columns: {}",
+                columns.join(", ")
+            ))
+        })?;
+
+        let path_column = has("ZPATH").then(|| "ZPATH".to_string()).ok_or_else(|| {
+            VoiceMemoError::UnsupportedSchema(format!(
+                "ZCLOUDRECORDING has no ZPATH column; found columns: {}",
+                columns.join(", ")
+            ))
+        })?;
+
+        let duration_expr = if has("ZDURATION") {
+            "ZDURATION".to_string()
+        } else {
+            eprintln!(
+                "warning: ZCLOUDRECORDING has no ZDURATION column; duration will default to 0.0"
+            );
+            "0.0".to_string()
+        };
+
+        Ok(Self {
+            title_expr,
+            date_column,
+            duration_expr,
+            path_column,
+        })
+    }
+}
+
+/// How a [`Clip`] is trimmed out of the source recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrimMode {
+    /// Fast-seek with `-ss` before `-i` and stream-copy. Cheapest, but the
+    /// output starts at the nearest preceding keyframe rather than exactly
+    /// `start_sec`, so it can begin a little early and drift in length.
+    StreamCopy,
+    /// Fast-seeks to the keyframe at or before `start_sec` (found via
+    /// `ffprobe`), then stream-copies with a second `-ss` for the remaining
+    /// `start_sec - keyframe_time`. Because the output is stream-copied, the
+    /// MP4 muxer records that remainder as an edit list (`edts`/`elst`)
+    /// instead of decoding it away, so players skip the pre-roll and stop
+    /// exactly `duration_sec` later while keeping stream-copy speed.
+    #[default]
+    EditList,
+    /// Decode and re-encode so the output file itself starts and ends
+    /// exactly on the requested boundaries, with no edit list involved.
+    Reencode,
+}
+
+/// An extracted clip from a Voice Memos recording.
+pub struct Clip;
+
+impl Clip {
+    /// Extracts `duration_sec` seconds starting at `start_sec` from `source_path`
+    /// into `dest_path` via `ffmpeg`, tagging it with `original_date`.
+    pub fn extract(
+        source_path: &Path,
+        dest_path: &Path,
+        start_sec: f64,
+        duration_sec: f64,
+        original_date: DateTime<Utc>,
+        trim_mode: TrimMode,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let comment = format!(
+            "Original recording date: {}",
+            original_date.format("%B %d, %Y at %I:%M:%S %p UTC")
+        );
+
+        let mut cmd = Command::new("ffmpeg");
+        match trim_mode {
+            TrimMode::StreamCopy => {
+                cmd.arg("-ss")
+                    .arg(format!("{}", start_sec))
+                    .arg("-i")
+                    .arg(source_path)
+                    .arg("-t")
+                    .arg(format!("{}", duration_sec))
+                    .arg("-c")
+                    .arg("copy");
+            }
+            TrimMode::EditList => {
+                // First -ss (before -i) fast-seeks to the keyframe so ffmpeg
+                // doesn't decode the whole file; second -ss (after -i) asks
+                // for the remaining start_sec - keyframe_time. Since the
+                // stream is copied rather than re-encoded, ffmpeg can't trim
+                // that remainder out of the samples, so the mp4 muxer
+                // records it as an edit list instead.
+                let keyframe_time = keyframe_at_or_before(source_path, start_sec)?;
+                cmd.arg("-ss")
+                    .arg(format!("{}", keyframe_time))
+                    .arg("-i")
+                    .arg(source_path)
+                    .arg("-ss")
+                    .arg(format!("{}", start_sec - keyframe_time))
+                    .arg("-t")
+                    .arg(format!("{}", duration_sec))
+                    .arg("-c")
+                    .arg("copy");
+            }
+            TrimMode::Reencode => {
+                // -ss after -i forces frame-accurate decoding, at the cost of
+                // a full re-encode instead of a stream copy.
+                cmd.arg("-i")
+                    .arg(source_path)
+                    .arg("-ss")
+                    .arg(format!("{}", start_sec))
+                    .arg("-t")
+                    .arg(format!("{}", duration_sec));
+            }
+        }
+
+        let output = cmd
+            .arg("-metadata")
+            .arg(format!("comment={}", comment))
+            .arg("-y") // Overwrite without asking
+            .arg(dest_path)
+            .output()?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("ffmpeg failed: {}", error).into());
+        }
+
+        Ok(())
+    }
+
+    /// A fresh path under the system temp directory for a one-off (uncached)
+    /// clip extraction. Distinct calls within the same process get distinct
+    /// paths, so pre-extracting clips ahead of the one currently playing
+    /// (as `radio` does) never overwrites a file still in use.
+    pub fn temp_path() -> PathBuf {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nonce = COUNTER.fetch_add(1, Ordering::Relaxed);
+        std::env::temp_dir().join(format!(
+            "voice_memo_clip_{}_{}.m4a",
+            std::process::id(),
+            nonce
+        ))
+    }
+}
+
+/// Finds the timestamp of the audio keyframe at or before `start_sec`, via
+/// `ffprobe`, so [`Clip::extract`]'s `EditList` mode knows where to seek for
+/// a keyframe-aligned stream copy.
+fn keyframe_at_or_before(
+    source_path: &Path,
+    start_sec: f64,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-select_streams")
+        .arg("a:0")
+        .arg("-show_entries")
+        .arg("packet=pts_time,flags")
+        .arg("-of")
+        .arg("csv=print_section=0")
+        .arg(source_path)
+        .output()?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe failed: {}", error).into());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let keyframe_time = stdout
+        .lines()
+        .filter_map(|line| {
+            let (pts_time, flags) = line.split_once(',')?;
+            flags.contains('K').then(|| pts_time.parse::<f64>().ok())?
+        })
+        .filter(|&pts_time| pts_time <= start_sec)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    if keyframe_time.is_finite() {
+        Ok(keyframe_time)
+    } else {
+        Err(format!("no keyframe at or before {}s in {:?}", start_sec, source_path).into())
+    }
+}
+
+/// Opens `clip_path` with `player` (an application name, as passed to `open -a`).
+pub fn play_clip(clip_path: &Path, player: &str) -> std::io::Result<()> {
+    Command::new("open")
+        .arg("-g")
+        .arg("-a")
+        .arg(player)
+        .arg(clip_path)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_data_epoch_converts_to_unix_epoch() {
+        // Core Data's reference date (2001-01-01 00:00:00 UTC) is exactly
+        // 978307200 seconds after the Unix epoch.
+        assert_eq!(core_data_to_unix_timestamp(0.0), 978_307_200);
+    }
+
+    #[test]
+    fn negative_core_data_timestamp_converts_to_unix_epoch() {
+        assert_eq!(core_data_to_unix_timestamp(-978_307_200.0), 0);
+    }
+
+    fn table_info_connection(columns: &[&str]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        let column_defs = columns
+            .iter()
+            .map(|c| format!("{} TEXT", c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        conn.execute(
+            &format!("CREATE TABLE ZCLOUDRECORDING ({})", column_defs),
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn schema_probe_prefers_encrypted_title_with_custom_label_fallback() {
+        let conn = table_info_connection(&[
+            "ZENCRYPTEDTITLE",
+            "ZCUSTOMLABEL",
+            "ZDATE",
+            "ZPATH",
+            "ZDURATION",
+        ]);
+        let schema = ZCloudRecordingSchema::probe(&conn).unwrap();
+        assert_eq!(
+            schema.title_expr,
+            "COALESCE(ZENCRYPTEDTITLE, ZCUSTOMLABEL)"
+        );
+        assert_eq!(schema.date_column, "ZDATE");
+        assert_eq!(schema.path_column, "ZPATH");
+        assert_eq!(schema.duration_expr, "ZDURATION");
+    }
+
+    #[test]
+    fn schema_probe_rejects_missing_required_columns() {
+        let conn = table_info_connection(&["ZENCRYPTEDTITLE"]);
+        assert!(matches!(
+            ZCloudRecordingSchema::probe(&conn),
+            Err(VoiceMemoError::UnsupportedSchema(_))
+        ));
+    }
+
+    #[test]
+    fn schema_probe_errors_when_table_is_missing() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(matches!(
+            ZCloudRecordingSchema::probe(&conn),
+            Err(VoiceMemoError::UnsupportedSchema(_))
+        ));
+    }
+}
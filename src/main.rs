@@ -1,165 +1,239 @@
-use chrono::{DateTime, Utc};
-use rand::Rng;
-use rusqlite::{Connection, OpenFlags, Result as SqlResult};
-use std::path::PathBuf;
-use std::process::Command;
-
-struct VoiceMemo {
-    title: String,
-    date: f64,
-    duration: f64,
-    path: String,
+mod radio;
+mod server;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use voice_memo_clip_player::{play_clip, Clip, ClipCache, TrimMode, VoiceMemo, VoiceMemoLibrary};
+
+/// CLI-facing mirror of [`TrimMode`], so the trim strategy is a `--trim-mode`
+/// value instead of a single boolean that can only pick between two of the
+/// three modes.
+#[derive(Clone, Copy, ValueEnum)]
+pub(crate) enum TrimModeArg {
+    StreamCopy,
+    EditList,
+    Reencode,
 }
 
-fn get_voice_memos_db_path() -> PathBuf {
-    let home = std::env::var("HOME").expect("HOME environment variable not set");
-    PathBuf::from(home)
-        .join("Library")
-        .join("Group Containers")
-        .join("group.com.apple.VoiceMemos.shared")
-        .join("Recordings")
-        .join("CloudRecordings.db")
+impl From<TrimModeArg> for TrimMode {
+    fn from(arg: TrimModeArg) -> Self {
+        match arg {
+            TrimModeArg::StreamCopy => TrimMode::StreamCopy,
+            TrimModeArg::EditList => TrimMode::EditList,
+            TrimModeArg::Reencode => TrimMode::Reencode,
+        }
+    }
 }
 
-fn get_voice_memos_dir() -> PathBuf {
-    let home = std::env::var("HOME").expect("HOME environment variable not set");
-    PathBuf::from(home)
-        .join("Library")
-        .join("Group Containers")
-        .join("group.com.apple.VoiceMemos.shared")
-        .join("Recordings")
-}
+/// Play a random clip from your Voice Memos library.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
 
-fn core_data_to_unix_timestamp(core_data_timestamp: f64) -> i64 {
-    // Core Data reference date is January 1, 2001 00:00:00 UTC
-    // Unix epoch is January 1, 1970 00:00:00 UTC
-    // Difference is 978307200 seconds
-    const CORE_DATA_EPOCH_OFFSET: f64 = 978307200.0;
-    (core_data_timestamp + CORE_DATA_EPOCH_OFFSET) as i64
+    #[command(flatten)]
+    play: PlayArgs,
 }
 
-fn get_all_voice_memos() -> SqlResult<Vec<VoiceMemo>> {
-    let db_path = get_voice_memos_db_path();
-
-    // Open database in READ-ONLY mode to prevent any modifications
-    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
-
-    let mut stmt = conn.prepare(
-        "SELECT ZENCRYPTEDTITLE, ZCUSTOMLABEL, ZDATE, ZDURATION, ZPATH FROM ZCLOUDRECORDING WHERE ZDURATION > 30.0"
-    )?;
-
-    const ZENCRYPTEDTITLE_COL_NUMBER: usize = 0;
-    const ZCUSTOMLABEL_COL_NUMBER: usize = 1;
-    const ZDATE_COL_NUM: usize = 2;
-    const ZDURATION_COL_NUM: usize = 3;
-    const ZPATH_COL_NUM: usize = 4;
-
-    let memos = stmt
-        .query_map([], |row| {
-            // Try ZENCRYPTEDTITLE first, fall back to ZCUSTOMLABEL, then "Untitled"
-            let title = row
-                .get(ZENCRYPTEDTITLE_COL_NUMBER)
-                .or_else(|_| row.get(ZCUSTOMLABEL_COL_NUMBER))
-                .unwrap_or_else(|_| "Untitled".to_string());
-
-            Ok(VoiceMemo {
-                title,
-                date: row.get(ZDATE_COL_NUM)?,
-                duration: row.get(ZDURATION_COL_NUM)?,
-                path: row.get(ZPATH_COL_NUM)?,
-            })
-        })?
-        .filter_map(|r| r.ok())
-        .collect();
-
-    Ok(memos)
+#[derive(Subcommand)]
+enum Command {
+    /// Delete cached clips older than a number of days
+    Purge {
+        /// Delete cache entries created more than this many days ago
+        #[arg(long, default_value_t = 30)]
+        older_than_days: u64,
+    },
+    /// Serve random clips over HTTP instead of playing them locally
+    Serve(ServeArgs),
+    /// Play an endless shuffle of clips back-to-back
+    Radio(RadioArgs),
 }
 
-fn extract_and_play_clip(
-    source_path: &PathBuf,
-    start_sec: f64,
-    duration_sec: f64,
-    original_date: DateTime<Utc>,
-) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    // Create a temporary file for the clip
-    let temp_dir = std::env::temp_dir();
-    let clip_path = temp_dir.join(format!("voice_memo_clip_{}.m4a", std::process::id()));
-
-    println!("Extracting 30-second clip with ffmpeg...");
-    println!("Original clip path: {}", source_path.to_str().unwrap());
-
-    // Format the date for the comment field
-    let comment = format!(
-        "Original recording date: {}",
-        original_date.format("%B %d, %Y at %I:%M:%S %p UTC")
-    );
+/// Filters shared by every subcommand that selects memos.
+#[derive(Args, Clone)]
+pub(crate) struct FilterArgs {
+    /// Only consider memos at least this many seconds long
+    #[arg(long, default_value_t = 30.0)]
+    min_duration: f64,
+
+    /// Only consider memos whose title contains this substring
+    #[arg(long)]
+    title_contains: Option<String>,
+
+    /// Only consider memos recorded on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    since: Option<NaiveDate>,
 
-    // Use ffmpeg to extract the clip and add metadata
-    let output = Command::new("ffmpeg")
-        .arg("-ss")
-        .arg(format!("{}", start_sec))
-        .arg("-i")
-        .arg(source_path)
-        .arg("-t")
-        .arg(format!("{}", duration_sec))
-        .arg("-c")
-        .arg("copy")
-        .arg("-metadata")
-        .arg(format!("comment={}", comment))
-        .arg("-y") // Overwrite without asking
-        .arg(&clip_path)
-        .output()?;
-
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("ffmpeg failed: {}", error).into());
+    /// Only consider memos recorded on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    until: Option<NaiveDate>,
+}
+
+impl FilterArgs {
+    pub(crate) fn matches(&self, memo: &VoiceMemo) -> bool {
+        if memo.duration < self.min_duration {
+            return false;
+        }
+        if let Some(substr) = &self.title_contains {
+            if !memo.title.contains(substr.as_str()) {
+                return false;
+            }
+        }
+        let date = memo.date_utc();
+        if let Some(since) = self.since {
+            if date < since.and_hms_opt(0, 0, 0).unwrap().and_utc() {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if date > until.and_hms_opt(23, 59, 59).unwrap().and_utc() {
+                return false;
+            }
+        }
+        true
     }
+}
 
-    println!("Clip saved to: {:?}\n", clip_path);
-    println!("Opening with VLC...\n");
+#[derive(Args)]
+struct PlayArgs {
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    /// Length of the extracted clip, in seconds
+    #[arg(long, default_value_t = 30.0)]
+    duration: f64,
 
-    // Open with VLC
-    Command::new("open")
-        .arg("-g")
-        .arg("-a")
-        .arg("VLC")
-        .arg(&clip_path)
-        .spawn()?;
+    /// Seed the random memo and offset choice, for reproducible output
+    #[arg(long)]
+    seed: Option<u64>,
 
-    Ok(clip_path)
+    /// Application to open the extracted clip with
+    #[arg(long, default_value = "VLC")]
+    player: String,
+
+    /// Print the selected clip without extracting or playing it
+    #[arg(long)]
+    dry_run: bool,
+
+    /// How to trim the clip out of the source recording
+    #[arg(long, value_enum, default_value_t = TrimModeArg::EditList)]
+    trim_mode: TrimModeArg,
+
+    /// Always extract a fresh clip instead of reusing a cached one
+    #[arg(long)]
+    no_cache: bool,
+}
+
+/// Arguments for the `serve` subcommand.
+#[derive(Args)]
+pub(crate) struct ServeArgs {
+    #[command(flatten)]
+    pub(crate) filters: FilterArgs,
+
+    /// Length of each served clip, in seconds
+    #[arg(long, default_value_t = 30.0)]
+    pub(crate) duration: f64,
+
+    /// Address to bind the HTTP server to
+    #[arg(long, default_value = "127.0.0.1")]
+    pub(crate) host: String,
+
+    /// Port to bind the HTTP server to
+    #[arg(long, default_value_t = 8080)]
+    pub(crate) port: u16,
+
+    /// How to trim each clip out of the source recording
+    #[arg(long, value_enum, default_value_t = TrimModeArg::EditList)]
+    pub(crate) trim_mode: TrimModeArg,
+
+    /// Always extract a fresh clip instead of reusing a cached one
+    #[arg(long)]
+    pub(crate) no_cache: bool,
+}
+
+/// Arguments for the `radio` subcommand.
+#[derive(Args)]
+pub(crate) struct RadioArgs {
+    #[command(flatten)]
+    pub(crate) filters: FilterArgs,
+
+    /// Length of each played clip, in seconds
+    #[arg(long, default_value_t = 30.0)]
+    pub(crate) duration: f64,
+
+    /// Application to play each clip with
+    #[arg(long, default_value = "VLC")]
+    pub(crate) player: String,
+
+    /// How to trim each clip out of the source recording
+    #[arg(long, value_enum, default_value_t = TrimModeArg::EditList)]
+    pub(crate) trim_mode: TrimModeArg,
+
+    /// Always extract fresh clips instead of reusing cached ones
+    #[arg(long)]
+    pub(crate) no_cache: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // NOTE: This script operates in READ-ONLY mode on Voice Memos
+    // NOTE: This tool operates in READ-ONLY mode on Voice Memos
     // - Database is opened with SQLITE_OPEN_READ_ONLY flag
     // - Audio files are only read, never modified
     // - A temporary clip file is created for playback
+    //   (with --no-cache; otherwise the clip is kept in the clip cache)
+
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Purge { older_than_days }) => purge(older_than_days),
+        Some(Command::Serve(args)) => server::run(args),
+        Some(Command::Radio(args)) => radio::run(args),
+        None => play(cli.play),
+    }
+}
 
+fn purge(older_than_days: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let cache = ClipCache::open(ClipCache::default_dir()?)?;
+    let purged = cache.purge_older_than(older_than_days)?;
+    println!(
+        "Purged {} cached clip(s) older than {} days.",
+        purged, older_than_days
+    );
+    Ok(())
+}
+
+fn play(args: PlayArgs) -> Result<(), Box<dyn std::error::Error>> {
     println!("Loading Voice Memos library...\n");
 
-    let memos = get_all_voice_memos()?;
+    let library = VoiceMemoLibrary::open_readonly()?;
+    let memos = library.filter(|memo| args.filters.matches(memo))?;
 
     if memos.is_empty() {
-        eprintln!("No voice memos found (longer than 30 seconds).");
+        eprintln!("No voice memos matched the given filters.");
         return Ok(());
     }
 
-    println!(
-        "Found {} voice memos longer than 30 seconds.\n",
-        memos.len()
-    );
+    println!("Found {} matching voice memos.\n", memos.len());
 
-    // Select a random memo
-    let mut rng = rand::thread_rng();
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
     let memo = &memos[rng.gen_range(0..memos.len())];
 
-    // Select a random start time (ensuring 30 seconds fits)
-    let max_start = memo.duration - 30.0;
-    let start_time = rng.gen_range(0.0..max_start);
-
-    // Convert Core Data timestamp to human-readable date
-    let unix_timestamp = core_data_to_unix_timestamp(memo.date);
-    let datetime = DateTime::<Utc>::from_timestamp(unix_timestamp, 0).unwrap_or_else(|| Utc::now());
+    // Select a random start time (ensuring the requested duration fits)
+    let max_start = memo.duration - args.duration;
+    if max_start < 0.0 {
+        eprintln!(
+            "Selected memo \"{}\" is shorter than the requested clip duration.",
+            memo.title
+        );
+        return Ok(());
+    }
+    let start_time = rng.gen_range(0.0..=max_start);
+    let datetime: DateTime<Utc> = memo.date_utc();
 
     // Display information
     println!("═══════════════════════════════════════════════════");
@@ -172,15 +246,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     println!("Duration: {:.1} seconds", memo.duration);
     println!(
-        "Clip:     {:.1}s - {:.1}s (30 seconds)",
+        "Clip:     {:.1}s - {:.1}s ({:.1} seconds)",
         start_time,
-        start_time + 30.0
+        start_time + args.duration,
+        args.duration
     );
     println!("═══════════════════════════════════════════════════\n");
 
-    // Construct full path
-    let recordings_dir = get_voice_memos_dir();
-    let full_path = recordings_dir.join(&memo.path);
+    if args.dry_run {
+        println!("Dry run: not extracting or playing the clip.");
+        return Ok(());
+    }
+
+    let full_path = library.recording_path(memo);
 
     if !full_path.exists() {
         eprintln!("Error: Recording file not found at {:?}", full_path);
@@ -188,11 +266,39 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
-    let clip_path = extract_and_play_clip(&full_path, start_time, 30.0, datetime)?;
+    let trim_mode: TrimMode = args.trim_mode.into();
+
+    let clip_path = if args.no_cache {
+        println!("Extracting clip with ffmpeg...");
+        println!("Original clip path: {}", full_path.to_str().unwrap());
+        let dest = Clip::temp_path();
+        Clip::extract(
+            &full_path,
+            &dest,
+            start_time,
+            args.duration,
+            datetime,
+            trim_mode,
+        )?;
+        dest
+    } else {
+        let cache = ClipCache::open(ClipCache::default_dir()?)?;
+        cache.get_or_extract(
+            &full_path,
+            &memo.path,
+            start_time,
+            args.duration,
+            datetime,
+            trim_mode,
+        )?
+    };
+
+    println!("Clip saved to: {:?}\n", clip_path);
+    println!("Opening with {}...\n", args.player);
+    play_clip(&clip_path, &args.player)?;
 
-    println!("VLC should now be playing the clip.");
-    println!("Temporary file will remain at: {:?}", clip_path);
-    println!("You can delete it manually or it will be cleaned up on reboot.");
+    println!("{} should now be playing the clip.", args.player);
+    println!("Clip file will remain at: {:?}", clip_path);
 
     Ok(())
 }
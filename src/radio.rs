@@ -0,0 +1,140 @@
+//! `radio` subcommand: an endless shuffle of clips played back-to-back,
+//! pre-extracting the next clip while the current one plays.
+
+use crate::RadioArgs;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use voice_memo_clip_player::{play_clip, Clip, ClipCache, TrimMode, VoiceMemo, VoiceMemoLibrary};
+
+struct Track {
+    memo: VoiceMemo,
+    start_time: f64,
+    clip_path: PathBuf,
+}
+
+pub(crate) fn run(args: RadioArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let library = VoiceMemoLibrary::open_readonly()?;
+    let memos =
+        library.filter(|memo| args.filters.matches(memo) && memo.duration >= args.duration)?;
+
+    let pool: Vec<(VoiceMemo, PathBuf)> = memos
+        .into_iter()
+        .map(|memo| {
+            let path = library.recording_path(&memo);
+            (memo, path)
+        })
+        .filter(|(_, path)| path.exists())
+        .collect();
+
+    if pool.is_empty() {
+        eprintln!("No downloaded voice memos matched the given filters.");
+        return Ok(());
+    }
+
+    // The channel holds one ready clip, so the worker extracts the next
+    // tracks while the current one plays: one clip waits in the channel and
+    // the worker can finish one more before blocking on send, so it never
+    // gets more than two clips ahead.
+    let (tx, rx) = mpsc::sync_channel::<Track>(1);
+    let duration = args.duration;
+    let trim_mode: TrimMode = args.trim_mode.into();
+    let no_cache = args.no_cache;
+
+    thread::spawn(move || extract_worker(pool, duration, trim_mode, no_cache, tx));
+
+    println!("Starting Voice Memo radio. Press Ctrl+C to stop.\n");
+    for track in rx {
+        println!(
+            "Now playing: \"{}\" ({}) [{:.1}s - {:.1}s]",
+            track.memo.title,
+            track.memo.date_utc().format("%B %d, %Y"),
+            track.start_time,
+            track.start_time + duration
+        );
+
+        play_clip(&track.clip_path, &args.player)?;
+        thread::sleep(Duration::from_secs_f64(duration));
+
+        if no_cache {
+            let _ = std::fs::remove_file(&track.clip_path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Extracts one clip ahead at a time, shuffling through the pool without
+/// repeating a memo until every memo in it has played.
+fn extract_worker(
+    pool: Vec<(VoiceMemo, PathBuf)>,
+    duration: f64,
+    trim_mode: TrimMode,
+    no_cache: bool,
+    tx: mpsc::SyncSender<Track>,
+) {
+    let mut rng = rand::thread_rng();
+    let mut remaining: Vec<usize> = Vec::new();
+
+    loop {
+        if remaining.is_empty() {
+            remaining = (0..pool.len()).collect();
+            remaining.shuffle(&mut rng);
+        }
+        let (memo, full_path) = &pool[remaining.pop().unwrap()];
+
+        let max_start = (memo.duration - duration).max(0.0);
+        let start_time = rng.gen_range(0.0..=max_start);
+
+        let clip_path = if no_cache {
+            let dest = Clip::temp_path();
+            match Clip::extract(
+                full_path,
+                &dest,
+                start_time,
+                duration,
+                memo.date_utc(),
+                trim_mode,
+            ) {
+                Ok(()) => dest,
+                Err(err) => {
+                    eprintln!("failed to extract \"{}\": {}", memo.title, err);
+                    continue;
+                }
+            }
+        } else {
+            let cached = ClipCache::default_dir()
+                .and_then(ClipCache::open)
+                .and_then(|cache| {
+                    cache.get_or_extract(
+                        full_path,
+                        &memo.path,
+                        start_time,
+                        duration,
+                        memo.date_utc(),
+                        trim_mode,
+                    )
+                });
+            match cached {
+                Ok(path) => path,
+                Err(err) => {
+                    eprintln!("failed to extract \"{}\": {}", memo.title, err);
+                    continue;
+                }
+            }
+        };
+
+        let track = Track {
+            memo: memo.clone(),
+            start_time,
+            clip_path,
+        };
+        if tx.send(track).is_err() {
+            // Receiver is gone; nothing left to do.
+            return;
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! `serve` subcommand: an HTTP endpoint that extracts and streams random
+//! clips instead of launching a local player, reusing the same library API
+//! as the CLI.
+
+use crate::ServeArgs;
+use rand::Rng;
+use tiny_http::{Header, Method, Request, Response, Server};
+use voice_memo_clip_player::{Clip, ClipCache, TrimMode, VoiceMemo, VoiceMemoLibrary};
+
+pub(crate) fn run(args: ServeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = format!("{}:{}", args.host, args.port);
+    let server = Server::http(&addr).map_err(|e| format!("failed to bind {}: {}", addr, e))?;
+
+    println!("Serving Voice Memos clips on http://{}", addr);
+    println!("  GET /random  - extract and stream a random matching clip");
+    println!("  GET /memos   - list matching memos as JSON");
+
+    for request in server.incoming_requests() {
+        let result = match (request.method(), request.url()) {
+            (Method::Get, "/random") => serve_random_clip(request, &args),
+            (Method::Get, "/memos") => serve_memo_list(request, &args),
+            _ => {
+                let response = Response::from_string("not found").with_status_code(404);
+                request.respond(response).map_err(Into::into)
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("request failed: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+fn serve_random_clip(request: Request, args: &ServeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let library = VoiceMemoLibrary::open_readonly()?;
+    let memos = library.filter(|memo| args.filters.matches(memo))?;
+    let candidates: Vec<&VoiceMemo> = memos
+        .iter()
+        .filter(|m| m.duration >= args.duration)
+        .collect();
+
+    let Some(memo) = pick_random(&candidates) else {
+        let response =
+            Response::from_string("no memos matched the given filters").with_status_code(404);
+        return request.respond(response).map_err(Into::into);
+    };
+
+    let full_path = library.recording_path(memo);
+    if !full_path.exists() {
+        let response =
+            Response::from_string("recording not downloaded locally").with_status_code(404);
+        return request.respond(response).map_err(Into::into);
+    }
+
+    let max_start = memo.duration - args.duration;
+    let start_time = rand::thread_rng().gen_range(0.0..=max_start);
+    let trim_mode: TrimMode = args.trim_mode.into();
+
+    let clip_path = if args.no_cache {
+        let dest = Clip::temp_path();
+        Clip::extract(
+            &full_path,
+            &dest,
+            start_time,
+            args.duration,
+            memo.date_utc(),
+            trim_mode,
+        )?;
+        dest
+    } else {
+        let cache = ClipCache::open(ClipCache::default_dir()?)?;
+        cache.get_or_extract(
+            &full_path,
+            &memo.path,
+            start_time,
+            args.duration,
+            memo.date_utc(),
+            trim_mode,
+        )?
+    };
+
+    stream_with_range(request, &clip_path, "audio/mp4")
+}
+
+fn serve_memo_list(request: Request, args: &ServeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let library = VoiceMemoLibrary::open_readonly()?;
+    let memos = library.filter(|memo| args.filters.matches(memo))?;
+
+    let entries: Vec<String> = memos
+        .iter()
+        .map(|memo| {
+            format!(
+                "{{\"title\":{},\"date\":\"{}\",\"duration\":{}}}",
+                json_string(&memo.title),
+                memo.date_utc().to_rfc3339(),
+                memo.duration
+            )
+        })
+        .collect();
+    let body = format!("[{}]", entries.join(","));
+
+    let response = Response::from_string(body).with_header(json_content_type());
+    request.respond(response).map_err(Into::into)
+}
+
+fn pick_random<'a>(memos: &[&'a VoiceMemo]) -> Option<&'a VoiceMemo> {
+    if memos.is_empty() {
+        return None;
+    }
+    let index = rand::thread_rng().gen_range(0..memos.len());
+    Some(memos[index])
+}
+
+/// Streams `path` to `request`, honoring a `Range:` header with a
+/// `206 Partial Content` response when present.
+fn stream_with_range(
+    request: Request,
+    path: &std::path::Path,
+    content_type: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let data = std::fs::read(path)?;
+    let total_len = data.len();
+
+    let range = request
+        .headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case("Range"))
+        .and_then(|h| parse_byte_range(h.value.as_str(), total_len));
+
+    let content_type_header =
+        Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap();
+    let accept_ranges_header = Header::from_bytes(&b"Accept-Ranges"[..], &b"bytes"[..]).unwrap();
+
+    let response = match range {
+        Some((start, end)) => {
+            let content_range = format!("bytes {}-{}/{}", start, end, total_len);
+            Response::from_data(data[start..=end].to_vec())
+                .with_status_code(206)
+                .with_header(content_type_header)
+                .with_header(accept_ranges_header)
+                .with_header(
+                    Header::from_bytes(&b"Content-Range"[..], content_range.as_bytes()).unwrap(),
+                )
+        }
+        None => Response::from_data(data)
+            .with_header(content_type_header)
+            .with_header(accept_ranges_header),
+    };
+
+    request.respond(response).map_err(Into::into)
+}
+
+/// Parses a `Range: bytes=start-end` header, clamped to `total_len`.
+///
+/// Also handles the RFC 7233 suffix form `bytes=-N` ("the last N bytes"),
+/// where `start_str` is empty and `end_str` is a byte count rather than an
+/// end offset.
+fn parse_byte_range(value: &str, total_len: usize) -> Option<(usize, usize)> {
+    let spec = value.strip_prefix("bytes=")?;
+    let (start_str, end_str) = spec.split_once('-')?;
+
+    if start_str.is_empty() {
+        let suffix_len: usize = end_str.parse().ok()?;
+        if suffix_len == 0 || total_len == 0 {
+            return None;
+        }
+        let start = total_len.saturating_sub(suffix_len);
+        return Some((start, total_len - 1));
+    }
+
+    let start: usize = start_str.parse().ok()?;
+    let end: usize = if end_str.is_empty() {
+        total_len.checked_sub(1)?
+    } else {
+        end_str
+            .parse::<usize>()
+            .ok()?
+            .min(total_len.checked_sub(1)?)
+    };
+
+    (start <= end).then_some((start, end))
+}
+
+fn json_content_type() -> Header {
+    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap()
+}
+
+fn json_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_plain_range() {
+        assert_eq!(parse_byte_range("bytes=0-499", 1000), Some((0, 499)));
+    }
+
+    #[test]
+    fn parses_an_open_ended_range() {
+        assert_eq!(parse_byte_range("bytes=500-", 1000), Some((500, 999)));
+    }
+
+    #[test]
+    fn parses_a_suffix_range() {
+        // "the last 500 bytes" of a 1000-byte file is 500..=999, not 0..=500.
+        assert_eq!(parse_byte_range("bytes=-500", 1000), Some((500, 999)));
+    }
+
+    #[test]
+    fn suffix_range_longer_than_file_clamps_to_the_whole_file() {
+        assert_eq!(parse_byte_range("bytes=-5000", 1000), Some((0, 999)));
+    }
+
+    #[test]
+    fn rejects_a_zero_length_suffix_range() {
+        assert_eq!(parse_byte_range("bytes=-0", 1000), None);
+    }
+
+    #[test]
+    fn clamps_an_end_past_the_file_length() {
+        assert_eq!(parse_byte_range("bytes=900-5000", 1000), Some((900, 999)));
+    }
+
+    #[test]
+    fn rejects_an_inverted_range() {
+        assert_eq!(parse_byte_range("bytes=500-100", 1000), None);
+    }
+
+    #[test]
+    fn rejects_a_missing_bytes_prefix() {
+        assert_eq!(parse_byte_range("0-499", 1000), None);
+    }
+}